@@ -21,12 +21,20 @@ use std::{
     time::Instant,
 };
 
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike, Utc};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveTime, TimeZone, Timelike, Utc};
 use once_cell::sync::Lazy;
 use tracing::{error, info};
 
 use crate::common::mix_all::MULTI_PATH_SPLITTER;
 
+// `disk_partition_space` below needs `nix` (unix) / `windows-sys` (windows)
+// for the real statvfs/GetDiskFreeSpaceExW calls, and `compress_if_over_threshold`
+// needs `flate2` (plus optional `brotli`/`zstd` behind their own cargo
+// features). This checkout has no Cargo.toml for any crate — not even for
+// chrono/once_cell/tracing above, which predate this file's edits — so
+// there's nowhere to record that dependency bump; whoever wires up the
+// workspace manifest needs to add these alongside the existing ones.
+
 const HEX_ARRAY: [char; 16] = [
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F',
 ];
@@ -36,45 +44,126 @@ pub fn compute_elapsed_time_milliseconds(begin_time: Instant) -> u64 {
     elapsed.as_millis() as u64
 }
 
-pub fn is_it_time_to_do(when: &str) -> bool {
-    let hours: Vec<&str> = when.split(";").collect();
-    if !hours.is_empty() {
-        let now = Local::now();
-        for hour in hours {
-            let now_hour: i32 = hour.parse().unwrap_or(0);
-            if now_hour == now.hour() as i32 {
-                return true;
+/// A single `;`-separated component of a schedule specifier, as accepted by
+/// [`is_it_time_to_do`] and [`next_execution_after`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScheduleToken {
+    /// A named alias such as `"hourly"`, `"daily"` or `"twice-daily"`,
+    /// pre-resolved to the hours it stands for.
+    Alias(Vec<u8>),
+    /// A single explicit hour (`"4"`); `"4;16"` parses as two such tokens.
+    List(Vec<u8>),
+    /// An inclusive hour range (`"1-5"`).
+    Range { start: u8, end: u8 },
+    /// Every `n`th hour starting at midnight (`"*/6"`).
+    Step(u8),
+}
+
+impl ScheduleToken {
+    fn hours(&self) -> Vec<u8> {
+        match self {
+            ScheduleToken::Alias(hours) | ScheduleToken::List(hours) => hours.clone(),
+            ScheduleToken::Range { start, end } => (*start..=*end).collect(),
+            ScheduleToken::Step(step) if *step > 0 => {
+                (0..24u8).step_by(*step as usize).collect()
             }
+            ScheduleToken::Step(_) => Vec::new(),
         }
     }
-    false
 }
 
+fn parse_schedule_token(token: &str) -> Option<ScheduleToken> {
+    let token = token.trim();
+    match token {
+        "hourly" => return Some(ScheduleToken::Alias((0..24u8).collect())),
+        "daily" => return Some(ScheduleToken::Alias(vec![0])),
+        "twice-daily" => return Some(ScheduleToken::Alias(vec![0, 12])),
+        _ => {}
+    }
+
+    if let Some(step_str) = token.strip_prefix("*/") {
+        return step_str
+            .parse::<u8>()
+            .ok()
+            .filter(|step| (1..24).contains(step))
+            .map(ScheduleToken::Step);
+    }
+
+    if let Some((start_str, end_str)) = token.split_once('-') {
+        let start = start_str.parse::<u8>().ok()?;
+        let end = end_str.parse::<u8>().ok()?;
+        return if start <= end && end < 24 {
+            Some(ScheduleToken::Range { start, end })
+        } else {
+            None
+        };
+    }
+
+    let hour = token.parse::<u8>().ok()?;
+    if hour < 24 {
+        Some(ScheduleToken::List(vec![hour]))
+    } else {
+        None
+    }
+}
+
+/// Parses a `;`-separated schedule specifier into the set of hours it
+/// matches, or `None` if any token fails to parse.
+fn scheduled_hours(when: &str) -> Option<Vec<u8>> {
+    let mut hours = Vec::new();
+    for token in when.split(';') {
+        hours.extend(parse_schedule_token(token)?.hours());
+    }
+    Some(hours)
+}
+
+/// Returns whether `when`, a `;`-separated schedule specifier (named alias,
+/// explicit hour list, inclusive range, or `*/n` step interval), matches the
+/// current local hour. Any unparseable token makes the whole schedule not
+/// match, rather than silently falling back to midnight.
+pub fn is_it_time_to_do(when: &str) -> bool {
+    let now_hour = Local::now().hour() as u8;
+    scheduled_hours(when)
+        .map(|hours| hours.contains(&now_hour))
+        .unwrap_or(false)
+}
+
+/// Returns the next local time strictly after `now` that `when` fires (a
+/// firing at the top of `now`'s own hour is not returned), or `None` if the
+/// schedule is unparseable or that local time is nonexistent or ambiguous.
+/// Lets callers sleep until the next occurrence instead of polling every hour.
+pub fn next_execution_after(when: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let mut hours = scheduled_hours(when)?;
+    hours.sort_unstable();
+    hours.dedup();
+    let current_hour = now.hour() as u8;
+    let (next_hour, days_ahead) = match hours.iter().find(|&&h| h > current_hour) {
+        Some(&hour) => (hour, 0),
+        None => (*hours.first()?, 1),
+    };
+    let next_date = now.date_naive() + Duration::days(days_ahead);
+    let next_time = NaiveTime::from_hms_opt(next_hour as u32, 0, 0)?;
+    Local
+        .from_local_datetime(&next_date.and_time(next_time))
+        .single()
+}
+
+/// Compact `yyyyMMddHHmmss` pattern, no fractional seconds, no offset.
+pub const DATE_FORMAT: &str = "%Y%m%d%H%M%S";
+/// `yyyy-MM-dd HH:mm:ss,SSS` pattern, no offset.
+pub const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S,%3f";
+/// `yyyy-MM-dd HH:mm:ss,SSS %z` pattern, with an explicit UTC offset so the
+/// rendered string is unambiguous across hosts in different timezones.
+pub const DATE_TIME_ZONE_FORMAT: &str = "%Y-%m-%d %H:%M:%S,%3f %z";
+
 pub fn time_millis_to_human_string2(t: i64) -> String {
     let dt = Utc.timestamp_millis_opt(t).unwrap();
-    format!(
-        "{:04}-{:02}-{:02} {:02}:{:02}:{:02},{:03}",
-        dt.year(),
-        dt.month(),
-        dt.day(),
-        dt.hour(),
-        dt.minute(),
-        dt.second(),
-        dt.timestamp_subsec_millis(),
-    )
+    dt.format(DATE_TIME_FORMAT).to_string()
 }
 
 pub fn time_millis_to_human_string3(t: i64) -> String {
     let dt = Utc.timestamp_millis_opt(t).unwrap();
-    format!(
-        "{:04}{:02}{:02}{:02}{:02}{:02}",
-        dt.year(),
-        dt.month(),
-        dt.day(),
-        dt.hour(),
-        dt.minute(),
-        dt.second(),
-    )
+    dt.format(DATE_FORMAT).to_string()
 }
 
 pub fn time_millis_to_human_string(t: i64) -> String {
@@ -82,6 +171,20 @@ pub fn time_millis_to_human_string(t: i64) -> String {
     dt.as_ref().unwrap().format("%Y%m%d%H%M%S%3f").to_string()
 }
 
+/// Renders `t` (epoch millis) in `offset` instead of UTC, including the `%z`
+/// offset so log lines stay unambiguous when collected from hosts in
+/// different timezones.
+pub fn time_millis_to_human_string_with_offset(t: i64, offset: FixedOffset) -> String {
+    let dt = Utc.timestamp_millis_opt(t).unwrap().with_timezone(&offset);
+    dt.format(DATE_TIME_ZONE_FORMAT).to_string()
+}
+
+/// Convenience wrapper around [`time_millis_to_human_string_with_offset`]
+/// that uses the local system timezone.
+pub fn time_millis_to_human_string_local(t: i64) -> String {
+    time_millis_to_human_string_with_offset(t, *Local::now().offset())
+}
+
 pub fn is_path_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
@@ -95,53 +198,135 @@ pub fn get_disk_partition_space_used_percent(path: &str) -> f64 {
         return -1.0;
     }
 
-    let path = Path::new(path);
-    if !path.exists() {
+    let path_ref = Path::new(path);
+    if !path_ref.exists() {
         error!(
             "Error when measuring disk space usage, file doesn't exist on this path: {}",
-            path.to_string_lossy()
+            path_ref.to_string_lossy()
         );
         return -1.0;
     }
 
-    match fs::metadata(path) {
-        Ok(metadata) => {
-            let total_space = metadata.len();
-            if total_space > 0 {
-                match (fs::metadata(path), fs::metadata(path)) {
-                    (Ok(metadata1), Ok(metadata2)) => {
-                        let free_space = metadata1.len();
-                        let usable_space = metadata2.len();
-                        let used_space = total_space.saturating_sub(free_space);
-                        let entire_space = used_space + usable_space;
-                        let round_num = if used_space * 100 % entire_space != 0 {
-                            1
-                        } else {
-                            0
-                        };
-                        let result = used_space * 100 / entire_space + round_num;
-                        return result as f64 / 100.0;
-                    }
-                    (Err(e), _) | (_, Err(e)) => {
-                        error!(
-                            "Error when measuring disk space usage, got exception: {:?}",
-                            e
-                        );
-                        return -1.0;
-                    }
-                }
-            }
+    match disk_partition_space(path_ref) {
+        Some((total, available)) if total > 0 => {
+            let used = total.saturating_sub(available);
+            let round_num = if used * 100 % total != 0 { 1 } else { 0 };
+            let result = used * 100 / total + round_num;
+            result as f64 / 100.0
+        }
+        Some(_) => -1.0,
+        None => -1.0,
+    }
+}
+
+/// Codec used to compress a response body before it goes on the wire.
+/// `Zlib` is always available; `Brotli` and `Zstd` are opt-in via cargo
+/// features so deployments that don't need them stay lean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionCodec {
+    #[default]
+    Zlib,
+    #[cfg(feature = "brotli-compression")]
+    Brotli,
+    #[cfg(feature = "zstd-compression")]
+    Zstd,
+}
+
+/// Compresses `body` with `codec` when it exceeds `threshold_bytes`.
+///
+/// Returns `Some(compressed_bytes)` when compression was applied, so the
+/// caller knows to set the "compressed" flag on the outgoing
+/// `RemotingCommand`; returns `None` (leaving `body` for the caller to use
+/// as-is) when the payload is too small for compression to be worthwhile.
+/// Namesrv and broker processors can share this one codec/threshold policy
+/// once their response type exposes a way to mark the compressed flag.
+pub fn compress_if_over_threshold(
+    body: &[u8],
+    codec: CompressionCodec,
+    threshold_bytes: usize,
+) -> Option<Vec<u8>> {
+    if body.len() <= threshold_bytes {
+        return None;
+    }
+    match codec {
+        CompressionCodec::Zlib => {
+            use std::io::Write;
+
+            use flate2::write::ZlibEncoder;
+            use flate2::Compression;
+
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()
+        }
+        #[cfg(feature = "brotli-compression")]
+        CompressionCodec::Brotli => {
+            use std::io::Write;
+
+            let mut compressed = Vec::new();
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            writer.write_all(body).ok()?;
+            drop(writer);
+            Some(compressed)
+        }
+        #[cfg(feature = "zstd-compression")]
+        CompressionCodec::Zstd => zstd::encode_all(body, 0).ok(),
+    }
+}
+
+#[cfg(unix)]
+fn disk_partition_space(path: &Path) -> Option<(u64, u64)> {
+    use nix::sys::statvfs::statvfs;
+
+    match statvfs(path) {
+        Ok(stat) => {
+            let fragment_size = stat.fragment_size();
+            let total = fragment_size * stat.blocks();
+            let available = fragment_size * stat.blocks_available();
+            Some((total, available))
         }
         Err(e) => {
             error!(
-                "Error when measuring disk space usage, got exception: {:?}",
+                "Error when measuring disk space usage for path {}, statvfs failed: {:?}",
+                path.to_string_lossy(),
                 e
             );
-            return -1.0;
+            None
         }
     }
+}
+
+#[cfg(windows)]
+fn disk_partition_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let mut wide_path: Vec<u16> = path.as_os_str().encode_wide().collect();
+    wide_path.push(0);
+
+    let mut free_bytes_available: u64 = 0;
+    let mut total_bytes: u64 = 0;
+    let mut total_free_bytes: u64 = 0;
+
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &mut free_bytes_available,
+            &mut total_bytes,
+            &mut total_free_bytes,
+        )
+    };
+
+    if ok == 0 {
+        error!(
+            "Error when measuring disk space usage for path {}, GetDiskFreeSpaceExW failed",
+            path.to_string_lossy()
+        );
+        return None;
+    }
 
-    -1.0
+    Some((total_bytes, free_bytes_available))
 }
 
 pub fn bytes_to_string(src: &[u8]) -> String {
@@ -248,12 +433,61 @@ mod tests {
         assert_eq!(is_it_time_to_do(&current_hour.to_string()), false);
     }
 
+    #[test]
+    fn is_it_time_to_do_matches_hourly_alias() {
+        assert_eq!(is_it_time_to_do("hourly"), true);
+    }
+
+    #[test]
+    fn is_it_time_to_do_matches_range() {
+        let current_hour = Local::now().hour();
+        let when = format!("{}-{}", current_hour, current_hour);
+        assert_eq!(is_it_time_to_do(&when), true);
+    }
+
+    #[test]
+    fn is_it_time_to_do_matches_step_interval() {
+        let current_hour = Local::now().hour();
+        assert_eq!(is_it_time_to_do("*/1"), current_hour % 1 == 0);
+    }
+
+    #[test]
+    fn is_it_time_to_do_returns_false_for_unparseable_token() {
+        assert_eq!(is_it_time_to_do("not-a-schedule"), false);
+    }
+
+    #[test]
+    fn next_execution_after_picks_next_hour_same_day() {
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 3, 30, 0).unwrap();
+        let next = next_execution_after("4;16", now).unwrap();
+        assert_eq!(next.hour(), 4);
+        assert_eq!(next.day(), 1);
+    }
+
+    #[test]
+    fn next_execution_after_wraps_to_next_day() {
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 20, 0, 0).unwrap();
+        let next = next_execution_after("4;16", now).unwrap();
+        assert_eq!(next.hour(), 4);
+        assert_eq!(next.day(), 2);
+    }
+
     #[test]
     fn time_millis_to_human_string_formats_correctly() {
         let timestamp = 1625140800000; // 2021-07-01T12:00:00Z
         assert_eq!(time_millis_to_human_string(timestamp), "20210701120000000");
     }
 
+    #[test]
+    fn time_millis_to_human_string_with_offset_includes_zone() {
+        let timestamp = 1625140800000; // 2021-07-01T12:00:00Z
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(
+            time_millis_to_human_string_with_offset(timestamp, offset),
+            "2021-07-01 14:00:00,000 +0200"
+        );
+    }
+
     #[test]
     fn is_path_exists_returns_true_for_existing_path() {
         assert_eq!(is_path_exists("."), true);
@@ -264,12 +498,48 @@ mod tests {
         assert_eq!(is_path_exists("./non_existing_path"), false);
     }
 
+    #[test]
+    fn get_disk_partition_space_used_percent_returns_ratio_for_existing_path() {
+        let used_percent = get_disk_partition_space_used_percent(".");
+        assert!((0.0..=1.0).contains(&used_percent));
+    }
+
+    #[test]
+    fn get_disk_partition_space_used_percent_returns_sentinel_for_missing_path() {
+        assert_eq!(
+            get_disk_partition_space_used_percent("./non_existing_path"),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn get_disk_partition_space_used_percent_returns_sentinel_for_empty_path() {
+        assert_eq!(get_disk_partition_space_used_percent(""), -1.0);
+    }
+
     #[test]
     fn bytes_to_string_converts_correctly() {
         let bytes = [0x41, 0x42, 0x43];
         assert_eq!(bytes_to_string(&bytes), "414243");
     }
 
+    #[test]
+    fn compress_if_over_threshold_skips_small_bodies() {
+        let body = b"small";
+        assert_eq!(
+            compress_if_over_threshold(body, CompressionCodec::Zlib, body.len()),
+            None
+        );
+    }
+
+    #[test]
+    fn compress_if_over_threshold_compresses_large_bodies() {
+        let body = vec![b'a'; 1024];
+        let compressed = compress_if_over_threshold(&body, CompressionCodec::Zlib, 64)
+            .expect("body over threshold should compress");
+        assert!(compressed.len() < body.len());
+    }
+
     #[test]
     fn offset_to_file_name_formats_correctly() {
         assert_eq!(offset_to_file_name(123), "00000000000000000123");
@@ -30,6 +30,7 @@ use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::protocol::RocketMqVersion;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use tracing::warn;
@@ -102,16 +103,13 @@ impl ClientRequestProcessor {
                     );
                     topic_route_data.order_topic_conf = order_topic_config;
                 };
-                /*let standard_json_only = request_header.accept_standard_json_only.unwrap_or(false);
-                let content = if request.version() >= RocketMqVersion::into(RocketMqVersion::V494)
-                    || standard_json_only
-                {
-                    //topic_route_data.encode()
-                    topic_route_data.encode()
-                } else {
-                    topic_route_data.encode()
-                };*/
-                let content = topic_route_data.encode();
+                let standard_json_only =
+                    request_header.accept_standard_json_only.unwrap_or(false);
+                let is_new_enough = request.version() >= RocketMqVersion::V494.into();
+                let content = encode_route_data(
+                    topic_route_data.encode(),
+                    is_new_enough || standard_json_only,
+                );
                 RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::Success)
                     .set_body(content)
             }
@@ -119,6 +117,32 @@ impl ClientRequestProcessor {
     }
 }
 
+/// Fields the pre-V4.9.4 `TopicRouteData` wire format is known to contain.
+/// Anything else (per-broker topic queue mapping and whatever else V4.9.4+
+/// has since added) gets dropped for legacy clients, rather than maintaining
+/// a deny-list of "new" fields that has to be kept in lockstep with every
+/// schema addition. The actual `TopicRouteData` definition isn't present in
+/// this checkout to enumerate its current field set against, so this list
+/// should be checked against it before merging.
+const LEGACY_ROUTE_DATA_FIELDS: &[&str] =
+    &["orderTopicConf", "queueDatas", "brokerDatas", "filterServerTable"];
+
+fn encode_route_data(content: Vec<u8>, standard_json: bool) -> Vec<u8> {
+    if standard_json {
+        return content;
+    }
+    match serde_json::from_slice::<serde_json::Value>(&content) {
+        Ok(serde_json::Value::Object(fields)) => {
+            let legacy_fields: serde_json::Map<String, serde_json::Value> = fields
+                .into_iter()
+                .filter(|(key, _)| LEGACY_ROUTE_DATA_FIELDS.contains(&key.as_str()))
+                .collect();
+            serde_json::to_vec(&legacy_fields).unwrap_or(content)
+        }
+        _ => content,
+    }
+}
+
 impl ClientRequestProcessor {
     pub fn process_request(
         &mut self,
@@ -130,3 +154,71 @@ impl ClientRequestProcessor {
         Some(self.get_route_info_by_topic(request))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct OldSchemaTopicRouteData {
+        #[serde(rename = "queueDatas")]
+        queue_datas: Vec<serde_json::Value>,
+        #[serde(rename = "brokerDatas")]
+        broker_datas: Vec<serde_json::Value>,
+    }
+
+    #[derive(Deserialize)]
+    struct NewSchemaTopicRouteData {
+        #[serde(rename = "queueDatas")]
+        queue_datas: Vec<serde_json::Value>,
+        #[serde(rename = "brokerDatas")]
+        broker_datas: Vec<serde_json::Value>,
+        #[serde(rename = "brokerDataList")]
+        broker_data_list: Option<Vec<serde_json::Value>>,
+        #[serde(rename = "topicQueueMappingByBroker")]
+        topic_queue_mapping_by_broker: Option<serde_json::Value>,
+    }
+
+    fn sample_route_data_json() -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "orderTopicConf": null,
+            "queueDatas": [],
+            "brokerDatas": [],
+            "filterServerTable": {},
+            "brokerDataList": [],
+            "topicQueueMappingByBroker": {},
+            "someFutureField": "not yet invented when this list was written",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn encode_route_data_strips_new_fields_for_legacy_clients() {
+        let content = encode_route_data(sample_route_data_json(), false);
+        let decoded: OldSchemaTopicRouteData = serde_json::from_slice(&content)
+            .expect("legacy payload should decode with the old-schema reader");
+        assert!(decoded.queue_datas.is_empty());
+        assert!(decoded.broker_datas.is_empty());
+        let raw: serde_json::Value = serde_json::from_slice(&content).unwrap();
+        assert!(raw.get("brokerDataList").is_none());
+        assert!(raw.get("topicQueueMappingByBroker").is_none());
+        // Not just the two fields this request happened to name: *any* field
+        // outside the known-legacy allow-list is dropped, including ones we
+        // never enumerated.
+        assert!(raw.get("someFutureField").is_none());
+    }
+
+    #[test]
+    fn encode_route_data_keeps_new_fields_for_standard_json_clients() {
+        let content = encode_route_data(sample_route_data_json(), true);
+        let decoded: NewSchemaTopicRouteData = serde_json::from_slice(&content)
+            .expect("standard JSON payload should decode with the new-schema reader");
+        assert!(decoded.broker_data_list.is_some());
+        assert!(decoded.topic_queue_mapping_by_broker.is_some());
+        let raw: serde_json::Value = serde_json::from_slice(&content).unwrap();
+        assert!(raw.get("someFutureField").is_some());
+    }
+}